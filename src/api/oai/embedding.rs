@@ -1,6 +1,6 @@
 use std::time::Duration;
 
-use axum::{extract::State, Json};
+use axum::{extract::State, http::StatusCode, Json};
 use futures_util::StreamExt;
 use serde::{Deserialize, Serialize};
 
@@ -16,16 +16,14 @@ pub struct EmbeddingRequest {
     embed_layer: usize,
 }
 
-impl From<EmbeddingRequest> for GenerateRequest {
-    fn from(value: EmbeddingRequest) -> Self {
-        let EmbeddingRequest { input, embed_layer } = value;
-        Self {
-            prompt: Vec::from(input).join(""),
-            max_tokens: 1,
-            embed: true,
-            embed_layer,
-            ..Default::default()
-        }
+/// Build a single-prompt generate job that only produces an embedding.
+fn embedding_job(prompt: String, embed_layer: usize) -> GenerateRequest {
+    GenerateRequest {
+        prompt,
+        max_tokens: 1,
+        embed: true,
+        embed_layer,
+        ..Default::default()
     }
 }
 
@@ -46,43 +44,101 @@ pub struct EmbeddingResponse {
 }
 
 /// `/api/oai/embeddings`, `/api/oai/v1/embeddings`.
+///
+/// Each element of `input` yields its own embedding vector, mirroring OpenAI's
+/// batch semantics: one generate job is dispatched per segment and the results
+/// are collected into `data` in request order.
 pub async fn embeddings(
     State(ThreadState(sender)): State<ThreadState>,
     Json(request): Json<EmbeddingRequest>,
-) -> Json<EmbeddingResponse> {
+) -> Result<Json<EmbeddingResponse>, StatusCode> {
     let info = request_info(sender.clone(), Duration::from_secs(1)).await;
     let model_name = info.reload.model_path.to_string_lossy().into_owned();
 
-    let (token_sender, token_receiver) = flume::unbounded();
-    let _ = sender.send(ThreadRequest::Generate {
-        request: request.into(),
-        tokenizer: info.tokenizer,
-        sender: token_sender,
-    });
-
-    let mut token_counter = TokenCounter::default();
-    let mut embedding = Vec::new();
-    let mut stream = token_receiver.into_stream();
-
-    while let Some(token) = stream.next().await {
-        match token {
-            Token::Stop(_, counter) => token_counter = counter,
-            Token::Embed(emb) => {
-                embedding = emb;
-                break;
+    let EmbeddingRequest { input, embed_layer } = request;
+    let inputs = Vec::from(input);
+    metrics::counter!(crate::metrics::EMBEDDING_REQUESTS).increment(inputs.len() as u64);
+
+    // Bound how long a single segment may occupy the worker, matching the
+    // WebSocket path so no transport leaves a runaway job unbounded.
+    let timeout = info
+        .reload
+        .slow_generation_timeout
+        .map(Duration::from_secs);
+
+    let mut data = Vec::with_capacity(inputs.len());
+    let mut counter = TokenCounter::default();
+
+    for (index, prompt) in inputs.into_iter().enumerate() {
+        let (token_sender, token_receiver) = flume::unbounded();
+        metrics::gauge!(crate::metrics::INFLIGHT_GENERATE).increment(1.0);
+        let _ = sender.send(ThreadRequest::Generate {
+            request: embedding_job(prompt, embed_layer),
+            tokenizer: info.tokenizer.clone(),
+            sender: token_sender,
+        });
+
+        let mut embedding = Vec::new();
+        let mut stream = token_receiver.into_stream();
+        let mut timed_out = false;
+
+        loop {
+            let token = match timeout {
+                Some(timeout) => match tokio::time::timeout(timeout, stream.next()).await {
+                    Ok(token) => token,
+                    Err(_) => {
+                        log::warn!("embedding generation timed out for input {index}, tearing down job");
+                        timed_out = true;
+                        break;
+                    }
+                },
+                None => stream.next().await,
+            };
+            let Some(token) = token else { break };
+            match token {
+                Token::Stop(_, segment) => {
+                    metrics::counter!(crate::metrics::PROMPT_TOKENS)
+                        .increment(segment.prompt as u64);
+                    metrics::counter!(crate::metrics::TOKENS_GENERATED)
+                        .increment(segment.completion as u64);
+                    metrics::histogram!(crate::metrics::REQUEST_LATENCY, "route" => "embeddings")
+                        .record(segment.duration.as_secs_f64());
+                    counter.prompt += segment.prompt;
+                    counter.completion += segment.completion;
+                    counter.total += segment.total;
+                    counter.duration += segment.duration;
+                }
+                Token::Embed(emb) => {
+                    embedding = emb;
+                    break;
+                }
+                _ => {}
             }
-            _ => {}
         }
+        metrics::gauge!(crate::metrics::INFLIGHT_GENERATE).decrement(1.0);
+
+        // A timed-out or otherwise empty segment must not be silently returned
+        // as a zero-length vector in the middle of the batch — surface it.
+        if embedding.is_empty() {
+            log::error!("input {index} produced no embedding");
+            return Err(if timed_out {
+                StatusCode::GATEWAY_TIMEOUT
+            } else {
+                StatusCode::INTERNAL_SERVER_ERROR
+            });
+        }
+
+        data.push(EmbeddingData {
+            object: "embedding".into(),
+            index,
+            embedding,
+        });
     }
 
-    Json(EmbeddingResponse {
+    Ok(Json(EmbeddingResponse {
         object: "list".into(),
         model: model_name,
-        data: vec![EmbeddingData {
-            object: "embedding".into(),
-            index: 0,
-            embedding,
-        }],
-        counter: token_counter,
-    })
-}
\ No newline at end of file
+        data,
+        counter,
+    }))
+}
@@ -0,0 +1,113 @@
+use futures_util::StreamExt;
+use salvo::prelude::*;
+use salvo::websocket::{Message, WebSocketUpgrade};
+
+use crate::middleware::{
+    FinishReason, GenerateRequest, ThreadRequest, ThreadState, Token, TokenCounter,
+};
+
+/// `/api/ws/generate`.
+///
+/// Upgrade to a WebSocket and stream a generation bidirectionally: the first
+/// inbound text frame is a `GenerateRequest`-shaped JSON body, after which each
+/// [`Token`] variant is sent back as its own frame. An inbound `"cancel"`
+/// control frame drops the token receiver and aborts the in-flight job.
+#[handler]
+pub async fn generate(
+    req: &mut Request,
+    res: &mut Response,
+    depot: &mut Depot,
+) -> Result<(), StatusError> {
+    let ThreadState(sender) = depot.obtain::<ThreadState>().unwrap().clone();
+    let info =
+        crate::api::request_info(sender.clone(), std::time::Duration::from_secs(1)).await;
+
+    WebSocketUpgrade::new()
+        .upgrade(req, res, move |mut ws| async move {
+            // The opening frame carries the generation parameters.
+            let request: GenerateRequest = match ws.recv().await {
+                Some(Ok(message)) => match serde_json::from_str(message.to_str().unwrap_or("")) {
+                    Ok(request) => request,
+                    Err(err) => {
+                        let _ = ws.send(Message::text(format!("invalid request: {err}"))).await;
+                        return;
+                    }
+                },
+                _ => return,
+            };
+
+            let (token_sender, token_receiver) = flume::unbounded();
+            metrics::gauge!(crate::metrics::INFLIGHT_GENERATE).increment(1.0);
+            let _ = sender.send(ThreadRequest::Generate {
+                request,
+                tokenizer: info.tokenizer.clone(),
+                sender: token_sender,
+            });
+
+            // The worker bounds the job itself via `slow_generation_timeout`;
+            // this client-side deadline is a belt-and-braces teardown for a
+            // disconnected peer, firing the same `Token::Stop` the worker would.
+            let timeout = info
+                .reload
+                .slow_generation_timeout
+                .map(std::time::Duration::from_secs);
+
+            let mut stream = token_receiver.into_stream();
+            loop {
+                let idle = async {
+                    match timeout {
+                        Some(timeout) => tokio::time::sleep(timeout).await,
+                        None => std::future::pending().await,
+                    }
+                };
+                tokio::select! {
+                    // A control frame from the client cancels the job: dropping
+                    // the receiver tears down the generation on the worker side.
+                    inbound = ws.recv() => match inbound {
+                        Some(Ok(message)) if message.to_str().map(|s| s == "cancel").unwrap_or(false) => {
+                            drop(stream);
+                            break;
+                        }
+                        None | Some(Err(_)) => break,
+                        _ => {}
+                    },
+                    _ = idle => {
+                        log::warn!("generation timed out, tearing down job");
+                        // Keep the frame protocol uniform: emit a real
+                        // `Token::Stop` so the peer decodes it like any other
+                        // frame. `FinishReason` has no timeout variant, so reuse
+                        // `Stop` and rely on the log above for the distinction.
+                        let stop = Token::Stop(FinishReason::Stop, TokenCounter::default());
+                        if let Ok(json) = serde_json::to_string(&stop) {
+                            let _ = ws.send(Message::text(json)).await;
+                        }
+                        drop(stream);
+                        break;
+                    }
+                    token = stream.next() => match token {
+                        Some(token) => {
+                            let stop = matches!(token, Token::Stop(..));
+                            if let Token::Stop(_, counter) = &token {
+                                metrics::counter!(crate::metrics::TOKENS_GENERATED)
+                                    .increment(counter.completion as u64);
+                            }
+                            match serde_json::to_string(&token) {
+                                Ok(json) => {
+                                    if ws.send(Message::text(json)).await.is_err() {
+                                        break;
+                                    }
+                                }
+                                Err(err) => log::error!("failed to serialize token: {err}"),
+                            }
+                            if stop {
+                                break;
+                            }
+                        }
+                        None => break,
+                    },
+                }
+            }
+            metrics::gauge!(crate::metrics::INFLIGHT_GENERATE).decrement(1.0);
+        })
+        .await
+}
@@ -0,0 +1,57 @@
+//! Prometheus metrics for ai00.
+//!
+//! A [`PrometheusHandle`] is installed as the global [`metrics`] recorder in
+//! `salvo_main`; the handlers and middleware then emit through the `metrics`
+//! facade (`counter!`, `histogram!`, `gauge!`) and `/metrics` renders the
+//! current snapshot in the text exposition format for Grafana to scrape.
+
+use metrics::{describe_counter, describe_gauge, describe_histogram, Unit};
+use metrics_exporter_prometheus::{PrometheusBuilder, PrometheusHandle};
+use salvo::prelude::*;
+
+/// Total completion tokens generated across all requests.
+pub const TOKENS_GENERATED: &str = "ai00_tokens_generated_total";
+/// Total prompt tokens consumed across all requests.
+pub const PROMPT_TOKENS: &str = "ai00_prompt_tokens_total";
+/// Request latency, in seconds, keyed by `route` label.
+pub const REQUEST_LATENCY: &str = "ai00_request_latency_seconds";
+/// Total embedding requests served.
+pub const EMBEDDING_REQUESTS: &str = "ai00_embedding_requests_total";
+/// Generate jobs currently occupying the worker.
+pub const INFLIGHT_GENERATE: &str = "ai00_inflight_generate_jobs";
+/// Total model (re)load events.
+pub const MODEL_LOADS: &str = "ai00_model_loads_total";
+
+/// Install the Prometheus recorder as the process-global metrics sink.
+///
+/// Must be called once, before any metric is emitted. Returns the handle used
+/// by [`metrics`] to render the exposition text.
+pub fn install_recorder() -> PrometheusHandle {
+    let handle = PrometheusBuilder::new()
+        .install_recorder()
+        .expect("install metrics recorder failed");
+    describe();
+    handle
+}
+
+/// Register descriptions and units so the rendered exposition carries `# HELP`
+/// and `# TYPE` lines even before a metric is first emitted.
+fn describe() {
+    describe_counter!(TOKENS_GENERATED, Unit::Count, "Completion tokens generated");
+    describe_counter!(PROMPT_TOKENS, Unit::Count, "Prompt tokens consumed");
+    describe_histogram!(REQUEST_LATENCY, Unit::Seconds, "Request latency in seconds");
+    describe_counter!(EMBEDDING_REQUESTS, Unit::Count, "Embedding requests served");
+    describe_gauge!(INFLIGHT_GENERATE, "Generate jobs currently occupying the worker");
+    describe_counter!(MODEL_LOADS, Unit::Count, "Model (re)load events");
+}
+
+/// `/metrics`.
+///
+/// Render the current metrics in Prometheus text exposition format.
+#[handler]
+pub async fn metrics(depot: &mut Depot) -> String {
+    match depot.obtain::<PrometheusHandle>() {
+        Ok(handle) => handle.render(),
+        Err(_) => String::new(),
+    }
+}
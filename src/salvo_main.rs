@@ -15,12 +15,64 @@ use salvo::Router;
 use std::{
     net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
     path::Path,
+    time::Duration,
 };
 
+/// Resolve once SIGTERM (rolling deploys) or Ctrl-C (interactive) arrives.
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        let mut term = signal(SignalKind::terminate()).expect("install SIGTERM handler failed");
+        tokio::select! {
+            _ = term.recv() => {}
+            _ = tokio::signal::ctrl_c() => {}
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = tokio::signal::ctrl_c().await;
+    }
+}
+
+/// Serve `app` on `acceptor`, shutting down gracefully on SIGTERM/Ctrl-C.
+///
+/// On signal we stop accepting new connections and give salvo up to `deadline`
+/// to finish serving the ones already in flight — including streaming
+/// generations, which are drained with their connection — before forcing the
+/// listeners closed.
+async fn serve_graceful<A>(acceptor: A, app: Router, deadline: Duration)
+where
+    A: salvo::conn::Acceptor + Send + 'static,
+{
+    let server = salvo::server::Server::new(acceptor);
+    let handle = server.handle();
+    tokio::spawn(async move {
+        shutdown_signal().await;
+        log::info!("shutdown signal received, draining in-flight generations...");
+        handle.stop_graceful(Some(deadline));
+    });
+    server.serve(app).await;
+}
+
+/// Compression hoop for non-streaming JSON routes (embeddings, model lists).
+///
+/// Scoped per-route on purpose: attaching it globally would wrap the SSE
+/// endpoints, and `min_length` cannot reliably skip chunked responses that
+/// carry no `Content-Length`, so streamed frames could get buffered/compressed.
+fn compression(min_length: u64) -> salvo::compression::Compression {
+    use salvo::compression::{Compression, CompressionLevel};
+    Compression::new()
+        .enable_gzip(CompressionLevel::Default)
+        .enable_brotli(CompressionLevel::Default)
+        .enable_zstd(CompressionLevel::Default)
+        .min_length(min_length as usize)
+}
+
 #[allow(clippy::collapsible_else_if)]
 pub async fn salvo_main() {
     use clap::CommandFactory;
-    use salvo::conn::rustls::{Keycert, RustlsConfig};
+    use salvo::conn::rustls::RustlsConfig;
 
     simple_logger::SimpleLogger::new()
         .with_level(log::LevelFilter::Warn)
@@ -30,6 +82,7 @@ pub async fn salvo_main() {
         .unwrap();
 
     let args = Args::parse();
+    let metrics = crate::metrics::install_recorder();
     let (sender, receiver) = flume::unbounded::<ThreadRequest>();
 
     let request: crate::middleware::ReloadRequest = {
@@ -43,11 +96,33 @@ pub async fn salvo_main() {
 
     let listen = request.listen.clone();
 
+    // A `unix:` address short-circuits the whole TCP/TLS/ACME selection below:
+    // the server binds a filesystem socket and its permissions gate access.
+    let uds = listen.as_ref().and_then(|listen| {
+        listen
+            .address
+            .as_deref()
+            .and_then(|address| address.strip_prefix("unix:"))
+            .map(|path| {
+                let remove = listen.remove_socket.unwrap_or(true);
+                (path.to_owned(), remove)
+            })
+    });
+
+    // Deadline salvo's graceful stop gives in-flight connections to finish.
+    let shutdown_deadline = Duration::from_secs(
+        listen
+            .as_ref()
+            .and_then(|listen| listen.shutdown_timeout)
+            .unwrap_or(30),
+    );
+
     tokio::task::spawn_blocking(move || model_route(receiver));
     let _ = sender.send(ThreadRequest::Reload {
         request: Box::new(request),
         sender: None,
     });
+    metrics::counter!(crate::metrics::MODEL_LOADS).increment(1);
 
     let serve_path = {
         let path = tempfile::tempdir()
@@ -86,36 +161,76 @@ pub async fn salvo_main() {
         }
     };
 
+    // Only plain HTTP or `unix:` deployments never touch certificates, so build
+    // the SNI resolver solely when TLS (directly or via ACME) is requested —
+    // otherwise a missing `assets/certs` must not abort boot. The resolver is
+    // shared between the TLS acceptor and the `/api/tls/reload` handler.
+    let tls_wanted = listen
+        .as_ref()
+        .map(|listen| {
+            let domain = listen.domain.as_deref().unwrap_or("local");
+            let acme = domain != "local" && listen.acme.unwrap_or_default();
+            acme || listen.tls.unwrap_or_default()
+        })
+        .unwrap_or(false);
+    let resolver = if tls_wanted {
+        Some(std::sync::Arc::new(
+            crate::tls::CertResolver::new("assets/certs").expect("load certificates failed"),
+        ))
+    } else {
+        None
+    };
+
     let cors = Cors::new()
         .allow_origin(AllowOrigin::any())
         .allow_methods(vec![Method::GET, Method::POST, Method::DELETE])
         .allow_headers("authorization")
         .into_handler();
 
+    // Compression is opt-out and applied only to the non-streaming routes below.
+    let compress_min = listen
+        .as_ref()
+        .and_then(|listen| listen.compression)
+        .unwrap_or(true)
+        .then(|| {
+            listen
+                .as_ref()
+                .and_then(|listen| listen.compression_min_length)
+                .unwrap_or(1024)
+        });
+    let compressed = |router: Router| match compress_min {
+        Some(min) => router.hoop(compression(min)),
+        None => router,
+    };
+
     let app = Router::new()
         //.hoop(CorsLayer::permissive())
         .hoop(Logger::new())
         .hoop(affix::inject(ThreadState(sender)))
+        .hoop(affix::inject(metrics))
         .hoop(cors)
         .push(Router::with_path("/api/adapters").get(api::adapters))
         .push(Router::with_path("/api/models/info").get(api::info))
         .push(Router::with_path("/api/models/load").post(api::load))
         .push(Router::with_path("/api/models/unload").get(api::unload))
         .push(Router::with_path("/api/models/state").get(api::state))
-        .push(Router::with_path("/api/models/list").get(api::models))
+        .push(compressed(Router::with_path("/api/models/list").get(api::models)))
         .push(Router::with_path("/api/files/unzip").post(api::unzip))
         .push(Router::with_path("/api/files/dir").post(api::dir))
         .push(Router::with_path("/api/files/ls").post(api::dir))
         .push(Router::with_path("/api/files/config/load").post(api::load_config))
         .push(Router::with_path("/api/files/config/save").post(api::save_config))
-        .push(Router::with_path("/api/oai/models").get(api::oai::models))
-        .push(Router::with_path("/api/oai/v1/models").get(api::oai::models))
+        .push(compressed(Router::with_path("/api/oai/models").get(api::oai::models)))
+        .push(compressed(Router::with_path("/api/oai/v1/models").get(api::oai::models)))
         .push(Router::with_path("/api/oai/completions").post(api::oai::completions))
         .push(Router::with_path("/api/oai/v1/completions").post(api::oai::completions))
         .push(Router::with_path("/api/oai/chat/completions").post(api::oai::chat_completions))
         .push(Router::with_path("/api/oai/v1/chat/completions").post(api::oai::chat_completions))
-        .push(Router::with_path("/api/oai/embeddings").post(api::oai::embeddings))
-        .push(Router::with_path("/api/oai/v1/embeddings").post(api::oai::embeddings));
+        .push(compressed(Router::with_path("/api/oai/embeddings").post(api::oai::embeddings)))
+        .push(compressed(Router::with_path("/api/oai/v1/embeddings").post(api::oai::embeddings)))
+        .push(Router::with_path("/api/tls/reload").post(crate::tls::reload_certs))
+        .push(Router::with_path("/metrics").get(crate::metrics::metrics))
+        .push(Router::with_path("/api/ws/generate").goal(api::ws::generate));
     // .push(
     //     Router::with_path("<**path>").get(StaticDir::new(serve_path).defaults(["index.html"])),
     // )
@@ -135,6 +250,33 @@ pub async fn salvo_main() {
             Router::with_path("<**path>").get(StaticDir::new(serve_path).defaults(["index.html"])),
         ); // this static serve should after the swagger.
 
+    // Expose the resolver to `/api/tls/reload` only when TLS is active.
+    let app = match &resolver {
+        Some(resolver) => app.hoop(affix::inject(resolver.clone())),
+        None => app,
+    };
+
+    if let Some((path, remove)) = uds {
+        use salvo::conn::unix::UnixListener;
+
+        // Stale sockets from a previous run would make `bind` fail, so clear it
+        // out first when we own the socket file.
+        if remove && Path::new(&path).exists() {
+            if let Err(err) = std::fs::remove_file(&path) {
+                log::error!("failed to remove stale socket {path}: {err}");
+            }
+        }
+
+        let acceptor = UnixListener::new(&path).bind().await;
+        log::info!("server started at unix:{path} without tls.");
+        serve_graceful(acceptor, app, shutdown_deadline).await;
+
+        if remove {
+            let _ = std::fs::remove_file(&path);
+        }
+        return;
+    }
+
     let (ipaddr, ipv6addr) = if args.ip.is_some() {
         (args.ip.unwrap(), None)
     } else if listen.is_some() {
@@ -192,20 +334,29 @@ pub async fn salvo_main() {
             let acceptor = acmelistener.join(TcpListener::new(v6addr)).bind().await;
             log::info!("server started at {addr} with acme and tls.");
             log::info!("server started at {v6addr} with acme and tls.");
-            salvo::server::Server::new(acceptor).serve(app).await;
+            serve_graceful(acceptor, app, shutdown_deadline).await;
         } else {
             let acceptor = acmelistener.bind().await;
             log::info!("server started at {addr} with acme and tls.");
-            salvo::server::Server::new(acceptor).serve(app).await;
+            serve_graceful(acceptor, app, shutdown_deadline).await;
         };
     } else if use_tls {
-        let config = RustlsConfig::new(
-            Keycert::new()
-                .cert_from_path("assets/certs/cert.pem")
-                .unwrap()
-                .key_from_path("assets/certs/key.pem")
-                .unwrap(),
-        );
+        // Pin the ring provider as the process default so the `ServerConfig`
+        // builder and the `CertifiedKey` signing in `tls.rs` share one
+        // `CryptoProvider` — otherwise rustls panics with "no process-level
+        // CryptoProvider". Ignore the error if another path already installed it.
+        let _ = rustls::crypto::ring::default_provider().install_default();
+
+        // Drive the handshake through the SNI resolver instead of a single
+        // static keypair, so multiple domains and live cert rotation work.
+        let resolver = resolver
+            .as_ref()
+            .expect("tls resolver must exist when tls is enabled")
+            .clone();
+        let server_config = rustls::ServerConfig::builder()
+            .with_no_client_auth()
+            .with_cert_resolver(resolver);
+        let config = RustlsConfig::from(server_config);
         let listener = TcpListener::new(addr).rustls(config.clone());
         if ipv6addr.is_some() {
             let v6addr = SocketAddr::new(IpAddr::V6(ipv6addr.unwrap()), bind_port);
@@ -218,14 +369,14 @@ pub async fn salvo_main() {
                 .await;
             log::info!("server started at {addr} with tls.");
             log::info!("server started at {v6addr} with tls.");
-            salvo::server::Server::new(acceptor).serve(app).await;
+            serve_graceful(acceptor, app, shutdown_deadline).await;
         } else {
             let acceptor = QuinnListener::new(config.clone(), addr)
                 .join(listener)
                 .bind()
                 .await;
             log::info!("server started at {addr} with tls.");
-            salvo::server::Server::new(acceptor).serve(app).await;
+            serve_graceful(acceptor, app, shutdown_deadline).await;
         };
     } else {
         if ipv6addr.is_some() {
@@ -234,11 +385,11 @@ pub async fn salvo_main() {
             let acceptor = TcpListener::new(addr).join(ipv6listener).bind().await;
             log::info!("server started at {addr} without tls.");
             log::info!("server started at {v6addr} without tls.");
-            salvo::server::Server::new(acceptor).serve(app).await;
+            serve_graceful(acceptor, app, shutdown_deadline).await;
         } else {
             log::info!("server started at {addr} without tls.");
             let acceptor = TcpListener::new(addr).bind().await;
-            salvo::server::Server::new(acceptor).serve(app).await;
+            serve_graceful(acceptor, app, shutdown_deadline).await;
         };
     };
 }
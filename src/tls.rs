@@ -0,0 +1,147 @@
+//! Dynamic, SNI-aware TLS certificate resolution with hot reload.
+//!
+//! A single [`CertResolver`] is installed into the rustls `ServerConfig` at
+//! boot. It keeps a `domain -> Arc<CertifiedKey>` map behind an [`ArcSwap`] so
+//! that re-reading the certificate directory and publishing a fresh map is a
+//! single atomic store — live connections keep the chain they negotiated with,
+//! while new handshakes immediately see the rotated certificates.
+
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::BufReader,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use arc_swap::ArcSwap;
+use rustls::{
+    server::{ClientHello, ResolvesServerCert},
+    sign::CertifiedKey,
+};
+use salvo::prelude::*;
+
+/// The domain used when a directory holds the fallback certificate, also
+/// returned when the ClientHello carries no (or an unknown) server name.
+const DEFAULT_DOMAIN: &str = "default";
+
+/// Resolves a certificate chain from the TLS ClientHello's server name.
+#[derive(Debug)]
+pub struct CertResolver {
+    dir: PathBuf,
+    certs: ArcSwap<HashMap<String, Arc<CertifiedKey>>>,
+}
+
+impl CertResolver {
+    /// Build a resolver serving the certificates found under `dir`.
+    ///
+    /// Each immediate sub-directory named `<domain>` is expected to contain a
+    /// `cert.pem`/`key.pem` pair; the directory named `default` provides the
+    /// fallback chain used when SNI does not match any domain.
+    pub fn new(dir: impl Into<PathBuf>) -> anyhow::Result<Self> {
+        let dir = dir.into();
+        let certs = ArcSwap::from_pointee(load_certs(&dir)?);
+        Ok(Self { dir, certs })
+    }
+
+    /// Re-read the certificate directory and atomically swap in the new map.
+    ///
+    /// Wired to `ThreadRequest::ReloadCerts` / `/api/tls/reload` so ACME
+    /// renewals and manual swaps take effect without dropping connections.
+    pub fn reload(&self) -> anyhow::Result<()> {
+        let certs = load_certs(&self.dir)?;
+        self.certs.store(Arc::new(certs));
+        Ok(())
+    }
+
+    fn lookup(&self, name: Option<&str>) -> Option<Arc<CertifiedKey>> {
+        let certs = self.certs.load();
+        name.and_then(|name| certs.get(name).cloned())
+            .or_else(|| certs.get(DEFAULT_DOMAIN).cloned())
+    }
+}
+
+impl ResolvesServerCert for CertResolver {
+    fn resolve(&self, client_hello: ClientHello) -> Option<Arc<CertifiedKey>> {
+        self.lookup(client_hello.server_name())
+    }
+}
+
+/// `/api/tls/reload`.
+///
+/// Re-read the certificate directory and atomically install the new map, so a
+/// renewed or swapped certificate is served on the next handshake.
+#[handler]
+pub async fn reload_certs(depot: &mut Depot) -> StatusCode {
+    match depot.obtain::<Arc<CertResolver>>() {
+        Ok(resolver) => match resolver.reload() {
+            Ok(_) => StatusCode::OK,
+            Err(err) => {
+                log::error!("failed to reload certificates: {err}");
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+        },
+        Err(_) => StatusCode::NOT_FOUND,
+    }
+}
+
+/// Scan `dir` for certificates.
+///
+/// Each `<domain>/` sub-directory contributes a `<domain> -> key` entry, and a
+/// top-level `cert.pem`/`key.pem` pair (the layout the baseline single-cert TLS
+/// branch used) is kept as the `default` fallback so existing deployments keep
+/// working after the upgrade. An empty result is an error rather than a silent
+/// all-handshakes-fail state.
+fn load_certs(dir: &Path) -> anyhow::Result<HashMap<String, Arc<CertifiedKey>>> {
+    let mut certs = HashMap::new();
+
+    // Preserve the pre-existing single-cert layout as the default chain.
+    let (cert, key) = (dir.join("cert.pem"), dir.join("key.pem"));
+    if cert.is_file() && key.is_file() {
+        match load_certified_key(&cert, &key) {
+            Ok(key) => {
+                certs.insert(DEFAULT_DOMAIN.to_owned(), Arc::new(key));
+            }
+            Err(err) => log::error!("failed to load default certificate: {err}"),
+        }
+    }
+
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        if !path.is_dir() {
+            continue;
+        }
+        let domain = match path.file_name().and_then(|name| name.to_str()) {
+            Some(domain) => domain.to_owned(),
+            None => continue,
+        };
+        // The top-level pair already owns the `default` slot; a `default/`
+        // sub-directory would clobber it with no defined precedence.
+        if domain == DEFAULT_DOMAIN {
+            log::warn!("ignoring `{DEFAULT_DOMAIN}/` subdir; it collides with the top-level default certificate");
+            continue;
+        }
+        match load_certified_key(&path.join("cert.pem"), &path.join("key.pem")) {
+            Ok(key) => {
+                certs.insert(domain, Arc::new(key));
+            }
+            Err(err) => log::error!("failed to load certificate for {domain}: {err}"),
+        }
+    }
+
+    if certs.is_empty() {
+        anyhow::bail!("no certificates found under {}", dir.display());
+    }
+    Ok(certs)
+}
+
+/// Assemble a [`CertifiedKey`] from a PEM certificate chain and private key.
+fn load_certified_key(cert: &Path, key: &Path) -> anyhow::Result<CertifiedKey> {
+    let chain = rustls_pemfile::certs(&mut BufReader::new(File::open(cert)?))
+        .collect::<Result<Vec<_>, _>>()?;
+    let key = rustls_pemfile::private_key(&mut BufReader::new(File::open(key)?))?
+        .ok_or_else(|| anyhow::anyhow!("no private key in {}", key.display()))?;
+    let signing_key = rustls::crypto::ring::sign::any_supported_type(&key)?;
+    Ok(CertifiedKey::new(chain, signing_key))
+}